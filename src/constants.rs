@@ -0,0 +1,8 @@
+pub const TAB_SPACES: usize = 4;
+
+/// Number of consecutive times `q` must be pressed to discard unsaved changes.
+pub const QUIT_TIMES: u8 = 2;
+
+/// How long a transient status-line message stays visible before it's
+/// automatically cleared.
+pub const STATUS_MESSAGE_DURATION: std::time::Duration = std::time::Duration::from_secs(3);
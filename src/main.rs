@@ -1,12 +1,10 @@
-#![feature(allocator_api)]
-
 mod constants;
 
 use constants::*;
 
 use crossterm::{
     cursor::{MoveTo, SetCursorStyle},
-    event::{read, Event, KeyCode, KeyModifiers},
+    event::{poll, read, Event, KeyCode, KeyModifiers},
     style::{Color, Print, PrintStyledContent, Stylize},
     terminal::{
         disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
@@ -14,8 +12,51 @@ use crossterm::{
     },
     ExecutableCommand,
 };
-use std::alloc::Global;
+use ropey::Rope;
+use std::env;
+use std::fs;
 use std::io::{stdout, Stdout, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// How often the background input thread polls crossterm for events while
+/// idle, so resize events and status-message expiry are noticed promptly
+/// even without a keypress.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A message delivered from the background input thread: either a
+/// crossterm event, or an idle wakeup with no event to report.
+enum Tick {
+    Input(Event),
+    Idle,
+}
+
+/// Polls crossterm for events on a background thread and forwards them
+/// over a channel, so `Editor::work` never blocks on a keypress and can
+/// react to resize events and status-message timeouts while idle.
+fn spawn_event_thread() -> Receiver<Tick> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || loop {
+        let tick = match poll(POLL_INTERVAL) {
+            Ok(true) => match read() {
+                Ok(event) => Tick::Input(event),
+                Err(_) => break,
+            },
+            Ok(false) => Tick::Idle,
+            Err(_) => break,
+        };
+
+        if tx.send(tick).is_err() {
+            break;
+        }
+    });
+
+    rx
+}
 
 #[derive(Debug)]
 enum Mode {
@@ -28,6 +69,11 @@ enum Actions {
     MoveDown,
     MoveLeft,
     MoveRight,
+    MoveWordForward,
+    MoveWordBackward,
+    MoveLineStart,
+    MoveLineFirstNonBlank,
+    MoveLineEnd,
     NewLine,
     Backspace,
     ModeToNormal,
@@ -35,34 +81,144 @@ enum Actions {
     AddChar(char),
     Tab,
     DeleteChar,
+    Save,
+    SaveAs,
+    Undo,
+    Redo,
     Exit,
 }
 
+/// The kind of run a grapheme cluster belongs to, used to find word
+/// boundaries for `MoveWordForward`/`MoveWordBackward`.
+#[derive(PartialEq, Clone, Copy)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punct,
+}
+
+/// A single reversible change applied to a `Buffer`, tracked by absolute
+/// rope char offset so it stays valid regardless of how surrounding text
+/// reshapes grapheme clusters. A newline is just another character, so
+/// splitting a line is an `Insert` like any other; deleting a whole
+/// grapheme cluster (e.g. a base char plus its combining marks) is a
+/// `Remove` of more than one char.
+#[derive(Clone)]
+enum EditOp {
+    Insert { idx: usize, char: char },
+    Remove { idx: usize, text: String },
+}
+
+/// One undo-able unit: a run of `EditOp`s performed as a single user action,
+/// plus the cursor position before and after they were applied.
+struct UndoGroup {
+    ops: Vec<EditOp>,
+    cursor_before: (usize, usize),
+    cursor_after: (usize, usize),
+}
+
+/// Text storage backed by a [`Rope`], indexed by `Editor`'s `(line, column)`
+/// cursor model rather than the rope's flat char offsets.
 struct Buffer {
-    lines: Vec<Vec<char>>,
+    rope: Rope,
 }
 
 impl Buffer {
-    fn insert(&mut self, x: usize, y: usize, char: char) {
-        if char != '\n' {
-            if self.lines.get_mut(y).is_none() {
-                self.lines.resize(y + 1, Vec::new_in(Global))
-            }
+    fn from_text(text: &str) -> Self {
+        Buffer {
+            rope: Rope::from_str(text),
+        }
+    }
 
-            let line = self.lines.get_mut(y).unwrap();
-            if line.get_mut(x).is_none() {
-                line.resize(x + 1, char::default())
+    /// The line's content with its trailing newline (if any) stripped.
+    fn line(&self, y: usize) -> String {
+        let mut line = self.rope.line(y).to_string();
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
             }
-            self.lines.get_mut(y).unwrap().insert(x, char);
         }
+        line
+    }
+
+    /// The number of grapheme clusters on line `y`, not counting its line
+    /// terminator.
+    fn grapheme_len(&self, y: usize) -> usize {
+        self.line(y).graphemes(true).count()
     }
 
-    fn remove(&mut self, x: usize, y: usize) {
-        let line = self.lines.get(y);
-        if line.is_some() && line.unwrap().get(x).is_some() {
-            self.lines.get_mut(y).unwrap().remove(x);
+    fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    /// The char offset, within line `y`, of the start of the `x`-th
+    /// grapheme cluster (or of the end of the line, if `x` is past it).
+    fn cluster_char_offset(&self, y: usize, x: usize) -> usize {
+        self.line(y)
+            .graphemes(true)
+            .take(x)
+            .map(|g| g.chars().count())
+            .sum()
+    }
+
+    /// Absolute rope char offset of the start of the `x`-th grapheme
+    /// cluster on line `y`.
+    fn char_offset(&self, y: usize, x: usize) -> usize {
+        self.rope.line_to_char(y) + self.cluster_char_offset(y, x)
+    }
+
+    /// Number of grapheme clusters that start within the first `char_count`
+    /// chars of line `y`; a cluster cut by `char_count` is counted too.
+    fn cluster_index_at_char_offset(&self, y: usize, char_count: usize) -> usize {
+        let line = self.line(y);
+        let byte_offset = line.chars().take(char_count).map(char::len_utf8).sum();
+        line[..byte_offset].graphemes(true).count()
+    }
+
+    /// Inserts `char` before the `x`-th grapheme cluster of line `y` and
+    /// returns the absolute rope char offset it was inserted at.
+    fn insert_char(&mut self, y: usize, x: usize, char: char) -> usize {
+        let idx = self.char_offset(y, x);
+        self.rope.insert_char(idx, char);
+        idx
+    }
+
+    /// Removes the whole grapheme cluster at `x` on line `y` and returns
+    /// its absolute rope char offset and the text that was removed.
+    fn remove_grapheme(&mut self, y: usize, x: usize) -> Option<(usize, String)> {
+        let text = self.line(y).graphemes(true).nth(x)?.to_string();
+        let idx = self.char_offset(y, x);
+        let end = (idx + text.chars().count()).min(self.rope.len_chars());
+        self.rope.remove(idx..end);
+        Some((idx, text))
+    }
+
+    /// Inserts `text` at an absolute rope char offset. Used by the undo
+    /// system, which tracks positions at the rope level.
+    fn insert_at(&mut self, idx: usize, text: &str) {
+        self.rope.insert(idx, text);
+    }
+
+    /// Removes `char_count` chars starting at an absolute rope char offset.
+    /// Used by the undo system, which tracks positions at the rope level.
+    fn remove_at(&mut self, idx: usize, char_count: usize) {
+        let end = (idx + char_count).min(self.rope.len_chars());
+        if idx < end {
+            self.rope.remove(idx..end);
         }
     }
+
+    /// Splits line `y` before grapheme `x`, carrying the rest of the line
+    /// onto a new line below it. Returns the absolute rope char offset the
+    /// newline was inserted at.
+    fn split_line(&mut self, y: usize, x: usize) -> usize {
+        self.insert_char(y, x, '\n')
+    }
+
+    fn to_text(&self) -> String {
+        self.rope.to_string()
+    }
 }
 
 struct Editor {
@@ -74,31 +230,235 @@ struct Editor {
 
     mode: Mode,
     stdout: Stdout,
-    debug_text: String,
+
+    /// The current status-line message and when it should be cleared, if
+    /// one is showing. Set via `set_status_message`.
+    status_message: Option<(String, Instant)>,
+
     size: (u16, u16),
     buffer: Buffer,
-    first_print_x: usize,
+
+    /// Index of the topmost visible buffer line.
+    row_offset: usize,
+    /// Index of the leftmost visible buffer column.
+    col_offset: usize,
+
+    file_path: Option<String>,
+    dirty: bool,
+    quit_times: u8,
+
+    undo_stack: Vec<UndoGroup>,
+    redo_stack: Vec<UndoGroup>,
 }
 
 impl Editor {
+    /// Width of the line-number gutter, including its trailing space.
+    fn gutter_width(&self) -> usize {
+        self.buffer.len_lines().max(1).ilog10() as usize + 1 + 1
+    }
+
+    /// Classifies a grapheme cluster by its first char, for word-motion
+    /// boundary detection.
+    fn cluster_class(cluster: &str) -> CharClass {
+        match cluster.chars().next() {
+            Some(char) if char.is_whitespace() => CharClass::Whitespace,
+            Some(char) if char.is_alphanumeric() || char == '_' => CharClass::Word,
+            _ => CharClass::Punct,
+        }
+    }
+
+    /// Moves the cursor to the start of the next word, skipping the rest of
+    /// the current run and any whitespace, wrapping onto following lines.
+    fn move_word_forward(&mut self) {
+        let mut y = self.cy;
+        let mut x = self.cx;
+        let mut clusters: Vec<String> = self
+            .buffer
+            .line(y)
+            .graphemes(true)
+            .map(String::from)
+            .collect();
+
+        if let Some(start_class) = clusters.get(x).map(|c| Self::cluster_class(c)) {
+            while clusters.get(x).map(|c| Self::cluster_class(c)) == Some(start_class) {
+                x += 1;
+            }
+        }
+
+        loop {
+            match clusters.get(x).map(|c| Self::cluster_class(c)) {
+                Some(CharClass::Whitespace) => x += 1,
+                Some(_) => break,
+                None => {
+                    if y + 1 >= self.buffer.len_lines() {
+                        break;
+                    }
+                    y += 1;
+                    x = 0;
+                    clusters = self
+                        .buffer
+                        .line(y)
+                        .graphemes(true)
+                        .map(String::from)
+                        .collect();
+                    if clusters.is_empty() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.cy = y;
+        self.cx = x;
+    }
+
+    /// Moves the cursor to the start of the previous word, skipping
+    /// whitespace and the rest of the current run, wrapping onto
+    /// preceding lines.
+    fn move_word_backward(&mut self) {
+        let mut y = self.cy;
+        let mut x = self.cx;
+        let mut clusters: Vec<String> = self
+            .buffer
+            .line(y)
+            .graphemes(true)
+            .map(String::from)
+            .collect();
+
+        loop {
+            if x > 0 {
+                x -= 1;
+                break;
+            } else if y > 0 {
+                y -= 1;
+                clusters = self
+                    .buffer
+                    .line(y)
+                    .graphemes(true)
+                    .map(String::from)
+                    .collect();
+                if clusters.is_empty() {
+                    continue;
+                }
+                x = clusters.len() - 1;
+                break;
+            } else {
+                return;
+            }
+        }
+
+        while clusters.get(x).map(|c| Self::cluster_class(c)) == Some(CharClass::Whitespace) {
+            if x > 0 {
+                x -= 1;
+            } else if y > 0 {
+                y -= 1;
+                clusters = self
+                    .buffer
+                    .line(y)
+                    .graphemes(true)
+                    .map(String::from)
+                    .collect();
+                if clusters.is_empty() {
+                    break;
+                }
+                x = clusters.len() - 1;
+            } else {
+                break;
+            }
+        }
+
+        if let Some(class) = clusters.get(x).map(|c| Self::cluster_class(c)) {
+            while x > 0 && clusters.get(x - 1).map(|c| Self::cluster_class(c)) == Some(class) {
+                x -= 1;
+            }
+        }
+
+        self.cy = y;
+        self.cx = x;
+    }
+
+    /// Segments a line into grapheme clusters paired with their on-screen
+    /// starting column, aligning each tab to the next `TAB_SPACES` stop and
+    /// sizing other clusters by their display width.
+    fn render_line(line: &str) -> Vec<(&str, usize)> {
+        let mut rendered = Vec::new();
+        let mut render_col = 0;
+
+        for cluster in line.graphemes(true) {
+            rendered.push((cluster, render_col));
+            render_col += if cluster == "\t" {
+                TAB_SPACES - (render_col % TAB_SPACES)
+            } else {
+                UnicodeWidthStr::width(cluster).max(1)
+            };
+        }
+
+        rendered
+    }
+
+    /// Maps a logical cursor cluster index `cx` on line `y` to its
+    /// on-screen column, accounting for tab expansion and display width.
+    fn render_x(&self, y: usize, cx: usize) -> usize {
+        let mut render_col = 0;
+
+        for cluster in self.buffer.line(y).graphemes(true).take(cx) {
+            render_col += if cluster == "\t" {
+                TAB_SPACES - (render_col % TAB_SPACES)
+            } else {
+                UnicodeWidthStr::width(cluster).max(1)
+            };
+        }
+
+        render_col
+    }
+
+    /// Clamps `row_offset`/`col_offset` so the cursor stays within the
+    /// visible `rows x cols` window. `rows` excludes the bottom row, which
+    /// is reserved for the status line.
+    fn scroll(&mut self) {
+        let rows = (self.size.1 as usize).saturating_sub(1);
+        let cols = (self.size.0 as usize).saturating_sub(self.gutter_width());
+        let render_x = self.render_x(self.cy, self.cx);
+
+        if self.cy < self.row_offset {
+            self.row_offset = self.cy;
+        } else if self.cy >= self.row_offset + rows {
+            self.row_offset = self.cy + 1 - rows;
+        }
+
+        if render_x < self.col_offset {
+            self.col_offset = render_x;
+        } else if render_x >= self.col_offset + cols {
+            self.col_offset = render_x + 1 - cols;
+        }
+    }
+
     fn draw(&mut self) {
+        self.scroll();
         self.status_line();
-        for (y, line) in self.buffer.lines.iter().enumerate() {
-            for (x, char) in ({
-                // TODO: // Shift the lines to the left when the current line is longer than the limit
-                // if self.buffer.len(y) > self.first_print_x {
-                //     line[self.first_print_x..].to_vec()
-                // } else {
-                //     Vec::new()
-                // }
-
-                line
-            })
-            .iter()
-            .enumerate()
-            {
-                _ = self.stdout.execute(MoveTo(x as u16, y as u16));
-                _ = self.stdout.execute(Print(char));
+
+        let rows = (self.size.1 as usize).saturating_sub(1);
+        let gutter_width = self.gutter_width();
+
+        for screen_y in 0..rows {
+            let y = screen_y + self.row_offset;
+            if y >= self.buffer.len_lines() {
+                break;
+            }
+
+            _ = self.stdout.execute(MoveTo(0, screen_y as u16));
+            _ = self
+                .stdout
+                .execute(Print(format!("{:>width$} ", y + 1, width = gutter_width - 1)));
+
+            let line = self.buffer.line(y);
+            for (cluster, x) in Self::render_line(&line) {
+                if x < self.col_offset || cluster == "\t" {
+                    continue;
+                }
+                let screen_x = gutter_width + (x - self.col_offset);
+                _ = self.stdout.execute(MoveTo(screen_x as u16, screen_y as u16));
+                _ = self.stdout.execute(Print(cluster));
             }
         }
 
@@ -107,29 +467,16 @@ impl Editor {
             Mode::Insert => SetCursorStyle::DefaultUserShape,
         });
 
-        // self.cx = match self.buffer.lines.get(self.cy) {
-        //     Some(line) if !line.is_empty() => {
-        //         // self.debug_text = format!("{:?} {:?}", self.buffer.cx, line);
-        //         self.buffer.cx
-        //             + ((TAB_SPACES - 1)
-        //                 * line[..=self.buffer.cx]
-        //                     .iter()
-        //                     .filter(|x| **x == '\t')
-        //                     .count())
-        //     }
-        //     _ => 0,
-        // };
-
+        let render_x = self.render_x(self.cy, self.cx);
         _ = self.stdout.execute(MoveTo(
-            // TODO: (self.cx.saturating_sub(self.first_print_x)) as u16,
-            self.cx as u16,
-            self.cy as u16,
+            (gutter_width + render_x - self.col_offset) as u16,
+            (self.cy - self.row_offset) as u16,
         ));
         _ = self.stdout.flush();
     }
 
     pub fn status_line(&mut self) {
-        _ = self.stdout.execute(MoveTo(0, self.size.1));
+        _ = self.stdout.execute(MoveTo(0, self.size.1.saturating_sub(1)));
 
         _ = self
             .stdout
@@ -149,80 +496,240 @@ impl Editor {
                     .on_cyan(),
             ))
             .unwrap()
-            // .queue(PrintStyledContent(
-            //     format!(" {:?}", {
-            //         let line = self.buffer.lines.get(self.cy);
-            //
-            //         if line.is_some() {
-            //             let char = line.unwrap().get(self.cx.saturating_sub(1));
-            //
-            //             if char.is_some() {
-            //                 char.unwrap().to_string()
-            //             } else {
-            //                 "".to_string()
-            //             }
-            //         } else {
-            //             "".to_string()
-            //         }
-            //     })
-            //     .to_uppercase()
-            //     .with(Color::Black)
-            //     .bold()
-            //     .on_cyan(),
-            // ))
-            // .unwrap()
             .execute(PrintStyledContent(
-                self.debug_text.clone().with(Color::Black).bold().on_cyan(),
+                format!(" {}", if self.dirty { "[+]" } else { "" })
+                    .with(Color::Black)
+                    .bold()
+                    .on_cyan(),
+            ))
+            .unwrap()
+            .execute(PrintStyledContent(
+                self.status_message
+                    .as_ref()
+                    .map_or("", |(text, _)| text.as_str())
+                    .with(Color::Black)
+                    .bold()
+                    .on_cyan(),
             ));
     }
 
+    /// Shows `text` in the status line until `duration` elapses, at which
+    /// point the next tick of the event loop clears it.
+    fn set_status_message(&mut self, text: impl Into<String>, duration: Duration) {
+        self.status_message = Some((text.into(), Instant::now() + duration));
+    }
+
+    /// Clears the status message once its timeout has elapsed. Returns
+    /// whether a message was cleared, so callers know to redraw.
+    fn clear_expired_status_message(&mut self) -> bool {
+        if matches!(&self.status_message, Some((_, expires_at)) if Instant::now() >= *expires_at) {
+            self.status_message = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn save(&mut self) {
+        if let Some(path) = &self.file_path {
+            if fs::write(path, self.buffer.to_text()).is_ok() {
+                self.dirty = false;
+                self.status_message = None;
+            } else {
+                self.set_status_message(" Failed to save", STATUS_MESSAGE_DURATION);
+            }
+        } else {
+            self.set_status_message(" No file name", STATUS_MESSAGE_DURATION);
+        }
+    }
+
+    /// Records `ops` as one undo group, coalescing a lone character insert
+    /// into the previous group if it directly continues it (so a word isn't
+    /// undone one letter at a time).
+    fn push_undo(&mut self, ops: Vec<EditOp>, cursor_before: (usize, usize)) {
+        self.redo_stack.clear();
+        let cursor_after = (self.cx, self.cy);
+
+        if let [EditOp::Insert { idx, char }] = ops.as_slice() {
+            let (idx, char) = (*idx, *char);
+            if char != '\n' {
+                if let Some(group) = self.undo_stack.last_mut() {
+                    if let Some(EditOp::Insert {
+                        idx: last_idx,
+                        char: last_char,
+                    }) = group.ops.last()
+                    {
+                        if *last_idx + 1 == idx && *last_char != '\n' {
+                            group.ops.push(ops[0].clone());
+                            group.cursor_after = cursor_after;
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.undo_stack.push(UndoGroup {
+            ops,
+            cursor_before,
+            cursor_after,
+        });
+    }
+
+    fn undo(&mut self) {
+        if let Some(group) = self.undo_stack.pop() {
+            for op in group.ops.iter().rev() {
+                match op {
+                    EditOp::Insert { idx, .. } => self.buffer.remove_at(*idx, 1),
+                    EditOp::Remove { idx, text } => self.buffer.insert_at(*idx, text),
+                }
+            }
+
+            (self.cx, self.cy) = group.cursor_before;
+            self.dirty = true;
+            self.redo_stack.push(group);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(group) = self.redo_stack.pop() {
+            for op in &group.ops {
+                match op {
+                    EditOp::Insert { idx, char } => {
+                        self.buffer.insert_at(*idx, &char.to_string())
+                    }
+                    EditOp::Remove { idx, text } => {
+                        self.buffer.remove_at(*idx, text.chars().count())
+                    }
+                }
+            }
+
+            (self.cx, self.cy) = group.cursor_after;
+            self.dirty = true;
+            self.undo_stack.push(group);
+        }
+    }
+
     fn work(&mut self) {
-        loop {
-            // self.draw();
-            //
-            if let Some(action) = self.handle_event(read().unwrap()) {
+        let ticks = spawn_event_thread();
+
+        while let Ok(tick) = ticks.recv() {
+            let expired = self.clear_expired_status_message();
+
+            let event = match tick {
+                Tick::Idle => {
+                    if expired {
+                        _ = self.stdout.execute(Clear(ClearType::All));
+                        self.draw();
+                    }
+                    continue;
+                }
+                Tick::Input(event) => event,
+            };
+            let is_resize = matches!(event, Event::Resize(..));
+
+            if let Some(action) = self.handle_event(event) {
+                if !matches!(action, Actions::Exit) {
+                    self.quit_times = QUIT_TIMES;
+                }
+
                 match action {
-                    Actions::Exit => break,
+                    Actions::Exit => {
+                        if self.dirty && self.quit_times > 0 {
+                            self.quit_times -= 1;
+                            if self.quit_times == 0 {
+                                break;
+                            }
+                            self.set_status_message(
+                                format!(
+                                    " Unsaved changes! Press q {} more time(s) to quit",
+                                    self.quit_times
+                                ),
+                                STATUS_MESSAGE_DURATION,
+                            );
+                        } else {
+                            break;
+                        }
+                    }
+                    Actions::Save => self.save(),
+                    // No rename prompt exists yet, so `SaveAs` just saves to the
+                    // current path, same as `Save`.
+                    Actions::SaveAs => self.save(),
+                    Actions::Undo => self.undo(),
+                    Actions::Redo => self.redo(),
                     Actions::MoveUp => {
                         self.cy = self.cy.saturating_sub(1);
-                        let previous_line = self.buffer.lines.get(self.cy.saturating_sub(1));
-                        if previous_line.is_some() {
-                            self.cx = previous_line.unwrap().len();
+                        if self.cy.saturating_sub(1) < self.buffer.len_lines() {
+                            self.cx = self.buffer.grapheme_len(self.cy.saturating_sub(1));
                             self.cy = self.cy.saturating_sub(1)
                         }
                     }
                     Actions::MoveDown => {
-                        let next_line = self.buffer.lines.get(self.cy + 1);
-                        if next_line.is_some() {
-                            self.cx = next_line.unwrap().len();
+                        if self.cy + 1 < self.buffer.len_lines() {
+                            self.cx = self.buffer.grapheme_len(self.cy + 1);
                             self.cy += 1;
                         }
                     }
                     Actions::MoveLeft => {
-                        let line = self.buffer.lines.get(self.cy).unwrap();
-                        let char = line.get(self.cx.saturating_sub(1));
-                        if char.is_some() {
+                        if self.cx.saturating_sub(1) < self.buffer.grapheme_len(self.cy) {
                             self.cx = self.cx.saturating_sub(1)
                         }
                     }
                     Actions::MoveRight => {
-                        let line = self.buffer.lines.get(self.cy).unwrap();
-                        let char = line.get(self.cx + 1);
-                        if char.is_some() {
+                        if self.cx + 1 < self.buffer.grapheme_len(self.cy) {
                             self.cx += 1
                         }
                     }
+                    Actions::MoveWordForward => self.move_word_forward(),
+                    Actions::MoveWordBackward => self.move_word_backward(),
+                    Actions::MoveLineStart => self.cx = 0,
+                    Actions::MoveLineFirstNonBlank => {
+                        let clusters: Vec<String> = self
+                            .buffer
+                            .line(self.cy)
+                            .graphemes(true)
+                            .map(String::from)
+                            .collect();
+                        self.cx = clusters
+                            .iter()
+                            .position(|c| Self::cluster_class(c) != CharClass::Whitespace)
+                            .unwrap_or(0);
+                    }
+                    Actions::MoveLineEnd => {
+                        self.cx = self.buffer.grapheme_len(self.cy).saturating_sub(1);
+                    }
                     Actions::NewLine => {
-                        self.buffer.insert(self.cx, self.cy, '\n');
+                        let cursor_before = (self.cx, self.cy);
+                        let idx = self.buffer.split_line(self.cy, self.cx);
                         self.cy += 1;
                         self.cx = 0;
+                        self.push_undo(vec![EditOp::Insert { idx, char: '\n' }], cursor_before);
+                        self.dirty = true;
                     }
                     Actions::Backspace => {
+                        let cursor_before = (self.cx, self.cy);
                         if self.cx > 0 {
-                            self.buffer.remove(self.cx - 1, self.cy);
-                            self.cx -= 1;
-                        } else {
-                            self.cy = self.cy.saturating_sub(1);
+                            if let Some((idx, text)) =
+                                self.buffer.remove_grapheme(self.cy, self.cx - 1)
+                            {
+                                self.cx -= 1;
+                                self.push_undo(vec![EditOp::Remove { idx, text }], cursor_before);
+                                self.dirty = true;
+                            }
+                        } else if self.cy > 0 {
+                            let previous_len = self.buffer.grapheme_len(self.cy - 1);
+                            let idx = self.buffer.char_offset(self.cy - 1, previous_len);
+                            self.buffer.remove_at(idx, 1);
+                            self.cy -= 1;
+                            self.cx = previous_len;
+                            self.push_undo(
+                                vec![EditOp::Remove {
+                                    idx,
+                                    text: "\n".to_string(),
+                                }],
+                                cursor_before,
+                            );
+                            self.dirty = true;
                         }
                     }
                     Actions::ModeToNormal => {
@@ -232,23 +739,53 @@ impl Editor {
                         self.mode = Mode::Insert;
                     }
                     Actions::AddChar(char) => {
-                        self.buffer.insert(self.cx, self.cy, char);
-                        self.cx += 1;
+                        let cursor_before = (self.cx, self.cy);
+                        let local_before = self.buffer.cluster_char_offset(self.cy, self.cx);
+                        let idx = self.buffer.insert_char(self.cy, self.cx, char);
+                        self.cx = self
+                            .buffer
+                            .cluster_index_at_char_offset(self.cy, local_before + 1);
+                        self.push_undo(vec![EditOp::Insert { idx, char }], cursor_before);
+                        self.dirty = true;
                     }
                     Actions::Tab => {
-                        for _ in 0..TAB_SPACES {
-                            self.buffer.insert(self.cx, self.cy, ' ');
-                            self.cx += 4;
-                        }
+                        let cursor_before = (self.cx, self.cy);
+                        let idx = self.buffer.insert_char(self.cy, self.cx, '\t');
+                        self.cx += 1;
+                        self.push_undo(vec![EditOp::Insert { idx, char: '\t' }], cursor_before);
+                        self.dirty = true;
                     }
                     Actions::DeleteChar => {
-                        self.buffer.remove(self.cx, self.cy);
+                        let cursor_before = (self.cx, self.cy);
+                        let grapheme_len = self.buffer.grapheme_len(self.cy);
+                        if self.cx < grapheme_len {
+                            if let Some((idx, text)) =
+                                self.buffer.remove_grapheme(self.cy, self.cx)
+                            {
+                                self.push_undo(vec![EditOp::Remove { idx, text }], cursor_before);
+                            }
+                            self.dirty = true;
+                        } else if self.cy + 1 < self.buffer.len_lines() {
+                            let idx = self.buffer.char_offset(self.cy, grapheme_len);
+                            self.buffer.remove_at(idx, 1);
+                            self.push_undo(
+                                vec![EditOp::Remove {
+                                    idx,
+                                    text: "\n".to_string(),
+                                }],
+                                cursor_before,
+                            );
+                            self.dirty = true;
+                        }
                     }
                 };
 
                 _ = self.stdout.execute(Clear(ClearType::All));
                 self.draw();
-            };
+            } else if is_resize || expired {
+                _ = self.stdout.execute(Clear(ClearType::All));
+                self.draw();
+            }
         }
     }
 
@@ -271,6 +808,25 @@ impl Editor {
                                     Some(Actions::ModeToInsert)
                                 }
                                 KeyCode::Char('d') | KeyCode::Delete => Some(Actions::DeleteChar),
+                                KeyCode::Char('s')
+                                    if event.modifiers.contains(KeyModifiers::CONTROL) =>
+                                {
+                                    Some(Actions::Save)
+                                }
+                                KeyCode::Char('W') => Some(Actions::SaveAs),
+                                // `w` is vim's word motion, not a save key; saving is
+                                // Ctrl-S, or `W` (which saves to the current path too).
+                                KeyCode::Char('w') => Some(Actions::MoveWordForward),
+                                KeyCode::Char('b') => Some(Actions::MoveWordBackward),
+                                KeyCode::Char('0') => Some(Actions::MoveLineStart),
+                                KeyCode::Char('^') => Some(Actions::MoveLineFirstNonBlank),
+                                KeyCode::Char('$') => Some(Actions::MoveLineEnd),
+                                KeyCode::Char('u') => Some(Actions::Undo),
+                                KeyCode::Char('r')
+                                    if event.modifiers.contains(KeyModifiers::CONTROL) =>
+                                {
+                                    Some(Actions::Redo)
+                                }
                                 _ => None,
                             },
 
@@ -312,22 +868,30 @@ impl Editor {
 }
 
 fn main() {
+    let file_path = env::args().nth(1);
+
+    let buffer = Buffer::from_text(
+        &file_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default(),
+    );
+
     let mut editor = Editor {
         cx: 0,
         cy: 0,
         mode: Mode::Normal,
         stdout: stdout(),
         size: size().unwrap(),
-        debug_text: String::new(),
-        buffer: Buffer {
-            lines: {
-                let mut vector = Vec::new();
-                vector.resize(1, Vec::new_in(Global));
-
-                vector
-            },
-        },
-        first_print_x: 0,
+        status_message: None,
+        buffer,
+        row_offset: 0,
+        col_offset: 0,
+        file_path,
+        dirty: false,
+        quit_times: QUIT_TIMES,
+        undo_stack: Vec::new(),
+        redo_stack: Vec::new(),
     };
 
     editor.run();